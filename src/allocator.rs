@@ -0,0 +1,38 @@
+use crate::{Error, Result};
+use core::ptr::NonNull;
+
+/// A pluggable allocator for FFI-exposed buffers
+///
+/// `StringArray`, `StringReader` and `expose_string_with` are all generic over
+/// an `Allocator`, so the memory they hand to (or receive from) C code is
+/// always allocated and freed with the *same* allocator. Mixing a buffer
+/// allocated with one `Allocator` with a different one passed to
+/// `from_raw_with` is undefined behavior.
+pub trait Allocator {
+    /// Allocates `size` bytes, returning `Error::AllocFailed` if the
+    /// allocator returned NULL
+    fn alloc(&self, size: usize) -> Result<NonNull<u8>>;
+
+    /// Deallocates memory previously returned by `alloc`
+    ///
+    /// # Safety
+    ///
+    /// * `ptr` must have been returned by `alloc` on this same allocator
+    /// * `ptr` must not have already been freed
+    unsafe fn free(&self, ptr: NonNull<u8>);
+}
+
+/// The default allocator, backed by libc's `malloc`/`free`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LibcAllocator;
+
+impl Allocator for LibcAllocator {
+    fn alloc(&self, size: usize) -> Result<NonNull<u8>> {
+        let ptr = unsafe { libc::malloc(size) }.cast::<u8>();
+        NonNull::new(ptr).ok_or(Error::AllocFailed { size })
+    }
+
+    unsafe fn free(&self, ptr: NonNull<u8>) {
+        libc::free(ptr.as_ptr().cast())
+    }
+}
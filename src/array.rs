@@ -1,4 +1,7 @@
-use std::ptr::NonNull;
+use crate::{Allocator, LibcAllocator, Result};
+use alloc::vec::Vec;
+use core::{mem, ptr, ptr::NonNull};
+use libc::memset;
 
 /// Null-terminated C array reader
 pub struct ArrayReader<T> {
@@ -81,11 +84,154 @@ impl<T> Iterator for ArrayIter<T> {
     }
 }
 
+/// A null-terminated C array builder
+///
+/// Has the same `new`/`into_raw`/`from_raw` lifecycle as `StringArray`.
+/// It heap-allocates each item plus the array backbone and, unlike
+/// `StringArray`, frees both when dropped, so typed structs can be handed
+/// to C and round-tripped back with `ArrayReader`.
+pub struct ArrayBuilder<T, A: Allocator = LibcAllocator> {
+    ptr: NonNull<*mut T>,
+    should_drop: bool,
+    has_dropped: bool,
+    alloc: A,
+}
+
+impl<T, A: Allocator> ArrayBuilder<T, A> {
+    /// Creates a new array from the given items, using the given allocator
+    ///
+    /// # Arguments
+    ///
+    /// * alloc - Allocator to use
+    /// * items - Items to move into the array
+    pub fn new_with<I>(alloc: A, items: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let items: Vec<T> = items.into_iter().collect();
+        let array_size = mem::size_of::<*mut T>() * (items.len() + 1);
+        let array_ptr = alloc.alloc(array_size)?.as_ptr().cast::<*mut T>();
+        unsafe { memset(array_ptr.cast(), 0, array_size) };
+        for (item_idx, item) in items.into_iter().enumerate() {
+            let item_ptr = alloc.alloc(mem::size_of::<T>())?.as_ptr().cast::<T>();
+            unsafe {
+                item_ptr.write(item);
+                *array_ptr.add(item_idx) = item_ptr;
+            }
+        }
+        Ok(Self {
+            ptr: unsafe { NonNull::new_unchecked(array_ptr) },
+            should_drop: true,
+            has_dropped: false,
+            alloc,
+        })
+    }
+
+    /// Returns a raw pointer to the array
+    ///
+    /// You MUST be sure that the array is deallocated
+    ///
+    /// Use `from_raw_with` method with `sould_drop=true`,
+    /// or make sure that C code deallocates a returned data.
+    pub fn into_raw(mut self) -> *mut *mut T {
+        self.should_drop = false;
+        self.ptr.as_ptr()
+    }
+
+    /// Constructs an array builder from raw pointer, using the given allocator
+    ///
+    /// # Safety
+    ///
+    /// Improper use may lead to memory problems.
+    /// For example, a double-free may occur
+    /// if the function is called twice on the same raw pointer.
+    ///
+    /// `alloc` MUST be the same allocator the memory behind `ptr` was
+    /// produced with, otherwise freeing it is undefined behavior.
+    ///
+    /// # Panics
+    ///
+    /// Pointer must be not NULL
+    ///
+    /// # Arguments
+    ///
+    /// * ptr - A pointer to C array
+    /// * should_drop - Should data be deallocated when `drop()` is called
+    /// * alloc - Allocator the memory behind `ptr` was produced with
+    pub unsafe fn from_raw_with(ptr: *mut *mut T, should_drop: bool, alloc: A) -> Self {
+        Self {
+            ptr: NonNull::new(ptr).expect("Pointer must be not NULL"),
+            should_drop,
+            has_dropped: false,
+            alloc,
+        }
+    }
+
+    fn free(&mut self) {
+        if self.should_drop && !self.has_dropped {
+            let mut item_idx = 0isize;
+            loop {
+                let item_ptr = unsafe { *self.ptr.as_ptr().offset(item_idx) };
+                if item_ptr.is_null() {
+                    break;
+                }
+                unsafe {
+                    ptr::drop_in_place(item_ptr);
+                    self.alloc.free(NonNull::new_unchecked(item_ptr.cast()));
+                }
+                item_idx += 1;
+            }
+            unsafe { self.alloc.free(self.ptr.cast()) }
+            self.has_dropped = true;
+        }
+    }
+}
+
+impl<T, A: Allocator> Drop for ArrayBuilder<T, A> {
+    fn drop(&mut self) {
+        self.free()
+    }
+}
+
+impl<T> ArrayBuilder<T, LibcAllocator> {
+    /// Creates a new array from the given items
+    ///
+    /// # Arguments
+    ///
+    /// * items - Items to move into the array
+    pub fn new<I>(items: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::new_with(LibcAllocator, items)
+    }
+
+    /// Constructs an array builder from raw pointer
+    ///
+    /// # Safety
+    ///
+    /// Improper use may lead to memory problems.
+    /// For example, a double-free may occur
+    /// if the function is called twice on the same raw pointer.
+    ///
+    /// # Panics
+    ///
+    /// Pointer must be not NULL
+    ///
+    /// # Arguments
+    ///
+    /// * ptr - A pointer to C array
+    /// * should_drop - Should data be deallocated when `drop()` is called
+    pub unsafe fn from_raw(ptr: *mut *mut T, should_drop: bool) -> Self {
+        Self::from_raw_with(ptr, should_drop, LibcAllocator)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use libc::{free, malloc};
-    use std::{mem, ptr::null_mut};
+    use std::{mem, ptr::null_mut, vec};
 
     #[repr(C)]
     struct Item {
@@ -143,4 +289,32 @@ mod tests {
     fn create_from_null() {
         let _: ArrayReader<Item> = unsafe { ArrayReader::new(null_mut()) };
     }
+
+    #[test]
+    fn test_build_and_read_array() {
+        let builder = ArrayBuilder::new((0..5).map(|value| Item { value })).unwrap();
+        let ptr = builder.into_raw();
+        let reader = unsafe { ArrayReader::new(ptr) };
+        let values: Vec<usize> = reader.into_iter().map(|x| unsafe { (*x).value }).collect();
+        assert_eq!(values, vec![0, 1, 2, 3, 4]);
+        unsafe { ArrayBuilder::from_raw(ptr, true) };
+    }
+
+    #[test]
+    fn test_drop_array_builder() {
+        let builder = ArrayBuilder::new((0..3).map(|value| Item { value })).unwrap();
+        let ptr = builder.into_raw();
+        let mut builder = unsafe { ArrayBuilder::from_raw(ptr, false) };
+        builder.free();
+        assert!(!builder.has_dropped);
+        let mut builder = unsafe { ArrayBuilder::from_raw(ptr, true) };
+        builder.free();
+        assert!(builder.has_dropped);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_array_builder_from_raw_null() {
+        let _: ArrayBuilder<Item> = unsafe { ArrayBuilder::from_raw(null_mut(), true) };
+    }
 }
@@ -1,14 +1,14 @@
-use std::{
-    error::Error as StdError,
-    ffi::{IntoStringError, NulError},
-    fmt,
-    result::Result as StdResult,
-    str::Utf8Error,
-};
+use alloc::ffi::{IntoStringError, NulError};
+use core::{error::Error as StdError, fmt, result::Result as StdResult, str::Utf8Error};
 
 /// Describes all errors that may occur
 #[derive(Debug)]
 pub enum Error {
+    /// Memory allocation failed
+    AllocFailed {
+        /// Size in bytes that was requested
+        size: usize,
+    },
     /// Error when converting a CString into a String
     IntoString(IntoStringError),
     /// Interior nul byte was found
@@ -41,6 +41,7 @@ impl fmt::Display for Error {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
         use self::Error::*;
         match self {
+            AllocFailed { size } => write!(out, "failed to allocate {} bytes", size),
             IntoString(ref err) => write!(out, "string conversion error: {}", err),
             NulByte(ref err) => write!(out, "nul byte error: {}", err),
             Null => write!(out, "got a NULL pointer"),
@@ -1,6 +1,12 @@
 //! Rust FFI utilities
+#![no_std]
 #![warn(missing_docs)]
 
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+mod allocator;
 mod error;
 
 /// Array-related utilities
@@ -9,4 +15,5 @@ pub mod array;
 /// String-related utilities
 pub mod string;
 
+pub use self::allocator::{Allocator, LibcAllocator};
 pub use self::error::{Error, Result};
@@ -1,31 +1,34 @@
-use crate::{Error, Result};
-use libc::{c_char, c_void, free, malloc, memset};
-use std::{
-    ffi::{CStr, CString},
-    mem,
-    ptr::NonNull,
+use crate::{Allocator, Error, LibcAllocator, Result};
+use alloc::{
+    ffi::CString,
+    string::{String, ToString},
+    vec::Vec,
 };
+use core::{ffi::CStr, mem, ptr::NonNull};
+use libc::{c_char, c_void, memset};
 
 /// A helper to read C string
-pub struct StringReader {
-    buf: Vec<i8>,
+pub struct StringReader<A: Allocator = LibcAllocator> {
+    ptr: NonNull<c_char>,
+    alloc: A,
 }
 
-impl StringReader {
-    /// Create a new reader
+impl<A: Allocator> StringReader<A> {
+    /// Create a new reader using the given allocator
     ///
     /// # Arguments
     ///
+    /// * alloc - Allocator to use
     /// * max_length - Maximum length of string
-    pub fn new(max_length: usize) -> Self {
-        Self {
-            buf: Vec::with_capacity(max_length),
-        }
+    pub fn try_new_with(alloc: A, max_length: usize) -> Result<Self> {
+        // One extra byte for the NUL terminator
+        let ptr = alloc.alloc(max_length.saturating_add(1))?.cast();
+        Ok(Self { ptr, alloc })
     }
 
     /// Get a pointer to read to
     pub fn get_target(&mut self) -> *mut c_char {
-        self.buf.as_mut_ptr()
+        self.ptr.as_ptr()
     }
 
     /// Get a result string
@@ -34,9 +37,9 @@ impl StringReader {
     }
 
     /// Get a result string or None if pointer is NULL
-    pub fn into_string_opt(mut self) -> Result<Option<String>> {
-        let ptr = self.buf.as_mut_ptr();
-        mem::forget(self.buf);
+    pub fn into_string_opt(self) -> Result<Option<String>> {
+        let ptr = self.ptr.as_ptr();
+        mem::forget(self);
         if ptr.is_null() {
             Ok(None)
         } else {
@@ -49,15 +52,14 @@ impl StringReader {
     }
 }
 
-/// A wrapper for null-terminated C string array
-pub struct StringArray {
-    ptr: NonNull<*const c_char>,
-    should_drop: bool,
-    has_dropped: bool,
+impl<A: Allocator> Drop for StringReader<A> {
+    fn drop(&mut self) {
+        unsafe { self.alloc.free(self.ptr.cast()) }
+    }
 }
 
-impl StringArray {
-    /// Creates a new string array
+impl StringReader<LibcAllocator> {
+    /// Create a new reader
     ///
     /// # Panics
     ///
@@ -65,32 +67,59 @@ impl StringArray {
     ///
     /// # Arguments
     ///
+    /// * max_length - Maximum length of string
+    pub fn new(max_length: usize) -> Self {
+        Self::try_new(max_length).expect("memory allocation failed")
+    }
+
+    /// Create a new reader, returning an error instead of panicking
+    /// if memory allocation fails
+    ///
+    /// # Arguments
+    ///
+    /// * max_length - Maximum length of string
+    pub fn try_new(max_length: usize) -> Result<Self> {
+        Self::try_new_with(LibcAllocator, max_length)
+    }
+}
+
+/// A wrapper for null-terminated C string array
+pub struct StringArray<A: Allocator = LibcAllocator> {
+    ptr: NonNull<*const c_char>,
+    should_drop: bool,
+    has_dropped: bool,
+    alloc: A,
+}
+
+impl<A: Allocator> StringArray<A> {
+    /// Creates a new string array using the given allocator
+    ///
+    /// # Arguments
+    ///
+    /// * alloc - Allocator to use
     /// * items - Items to copy
-    pub fn new<T, I>(items: T) -> Result<Self>
+    pub fn new_with<T, I>(alloc: A, items: T) -> Result<Self>
     where
         T: IntoIterator<Item = I>,
         I: AsRef<str>,
     {
         let items: Vec<I> = items.into_iter().collect();
         let array_size = mem::size_of::<*const c_char>() * (items.len() + 1);
-        let array_ptr = unsafe {
-            let ptr = malloc(array_size);
-            assert!(!ptr.is_null());
-            memset(ptr, 0, array_size);
-            ptr as *mut *const c_char
-        };
+        let array_ptr = alloc.alloc(array_size)?.as_ptr().cast::<*const c_char>();
+        unsafe { memset(array_ptr.cast(), 0, array_size) };
         for (item_idx, item_data) in items.iter().enumerate() {
             let item_idx = item_idx as isize;
             let item_data = item_data.as_ref().as_bytes();
             unsafe {
                 let item_ptr = array_ptr.offset(item_idx);
-                *item_ptr = expose_string(item_data)?;
+                *item_ptr = expose_string_with(&alloc, item_data)?;
             }
         }
         Ok(Self {
             ptr: unsafe { NonNull::new_unchecked(array_ptr) },
             should_drop: true,
             has_dropped: false,
+            alloc,
         })
     }
 
@@ -98,14 +127,14 @@ impl StringArray {
     ///
     /// You MUST be sure that string array is deallocated
     ///
-    /// Use `from_raw` method with `sould_drop=true`,
+    /// Use `from_raw_with` method with `sould_drop=true`,
     /// or make sure that C code deallocates a returned data.
     pub fn into_raw(mut self) -> *mut *const c_char {
         self.should_drop = false;
         self.ptr.as_ptr()
     }
 
-    /// Constructs a string array from raw pointer
+    /// Constructs a string array from raw pointer, using the given allocator
     ///
     /// # Safety
     ///
@@ -113,6 +142,9 @@ impl StringArray {
     /// For example, a double-free may occur
     /// if the function is called twice on the same raw pointer.
     ///
+    /// `alloc` MUST be the same allocator the memory behind `ptr` was
+    /// produced with, otherwise freeing it is undefined behavior.
+    ///
     /// # Panics
     ///
     /// Pointer must be not NULL
@@ -121,45 +153,82 @@ impl StringArray {
     ///
     /// * ptr - A pointer to C string array
     /// * should_drop - Should data be deallocated when `drop()` is called
-    pub unsafe fn from_raw(ptr: *mut *const c_char, should_drop: bool) -> Self {
+    /// * alloc - Allocator the memory behind `ptr` was produced with
+    pub unsafe fn from_raw_with(ptr: *mut *const c_char, should_drop: bool, alloc: A) -> Self {
         Self {
             ptr: NonNull::new(ptr).expect("Pointer must be not NULL"),
             should_drop,
             has_dropped: false,
+            alloc,
         }
     }
 
     fn free(&mut self) {
         if self.should_drop && !self.has_dropped {
-            unsafe { free(self.ptr.as_ptr().cast()) }
+            unsafe { self.alloc.free(self.ptr.cast()) }
             self.has_dropped = true;
         }
     }
 }
 
-impl Drop for StringArray {
+impl<A: Allocator> Drop for StringArray<A> {
     fn drop(&mut self) {
         self.free()
     }
 }
 
-impl IntoIterator for StringArray {
+impl<A: Allocator> IntoIterator for StringArray<A> {
     type Item = Result<String>;
-    type IntoIter = StringArrayIter;
+    type IntoIter = StringArrayIter<A>;
 
     fn into_iter(self) -> Self::IntoIter {
         StringArrayIter::new(self)
     }
 }
 
+impl StringArray<LibcAllocator> {
+    /// Creates a new string array
+    ///
+    /// # Arguments
+    ///
+    /// * items - Items to copy
+    pub fn new<T, I>(items: T) -> Result<Self>
+    where
+        T: IntoIterator<Item = I>,
+        I: AsRef<str>,
+    {
+        Self::new_with(LibcAllocator, items)
+    }
+
+    /// Constructs a string array from raw pointer
+    ///
+    /// # Safety
+    ///
+    /// Improper use may lead to memory problems.
+    /// For example, a double-free may occur
+    /// if the function is called twice on the same raw pointer.
+    ///
+    /// # Panics
+    ///
+    /// Pointer must be not NULL
+    ///
+    /// # Arguments
+    ///
+    /// * ptr - A pointer to C string array
+    /// * should_drop - Should data be deallocated when `drop()` is called
+    pub unsafe fn from_raw(ptr: *mut *const c_char, should_drop: bool) -> Self {
+        Self::from_raw_with(ptr, should_drop, LibcAllocator)
+    }
+}
+
 /// Iterator over StringArray
-pub struct StringArrayIter {
-    array: StringArray,
+pub struct StringArrayIter<A: Allocator = LibcAllocator> {
+    array: StringArray<A>,
     current_index: isize,
 }
 
-impl StringArrayIter {
-    fn new(array: StringArray) -> Self {
+impl<A: Allocator> StringArrayIter<A> {
+    fn new(array: StringArray<A>) -> Self {
         Self {
             array,
             current_index: 0,
@@ -167,7 +236,7 @@ impl StringArrayIter {
     }
 }
 
-impl Iterator for StringArrayIter {
+impl<A: Allocator> Iterator for StringArrayIter<A> {
     type Item = Result<String>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -191,16 +260,23 @@ impl Iterator for StringArrayIter {
 /// Use this function if you are unable to deallocate string in Rust code.
 /// You MUST be sure that string is deallocated.
 pub fn expose_string<T: Into<Vec<u8>>>(input: T) -> Result<*const c_char> {
+    expose_string_with(&LibcAllocator, input)
+}
+
+/// Copies a rust string to a newly allocated C String, using the given allocator
+///
+/// Use this function if you are unable to deallocate string in Rust code.
+/// You MUST be sure that string is deallocated with the same allocator.
+pub fn expose_string_with<A: Allocator, T: Into<Vec<u8>>>(
+    alloc: &A,
+    input: T,
+) -> Result<*const c_char> {
     let input = input.into();
     let size = input.len() + 1;
     let input = CString::new(input)?;
     let src = input.as_ptr().cast::<c_void>();
-    let dest = unsafe {
-        let p = malloc(size);
-        assert!(!p.is_null());
-        src.copy_to_nonoverlapping(p, size);
-        p.cast::<i8>()
-    };
+    let dest = alloc.alloc(size)?.as_ptr().cast::<c_char>();
+    unsafe { src.copy_to_nonoverlapping(dest.cast(), size) };
     Ok(dest)
 }
 
@@ -208,7 +284,7 @@ pub fn expose_string<T: Into<Vec<u8>>>(input: T) -> Result<*const c_char> {
 mod tests {
     use super::*;
     use libc::strcpy;
-    use std::ptr::null_mut;
+    use std::{ptr::null_mut, vec};
 
     #[test]
     fn test_read_and_write_string() {
@@ -248,4 +324,26 @@ mod tests {
     fn test_string_array_from_raw_null() {
         let _ = unsafe { StringArray::from_raw(null_mut(), true) };
     }
+
+    #[test]
+    fn test_custom_allocator() {
+        #[derive(Clone, Copy, Default)]
+        struct PassthroughAllocator;
+
+        impl Allocator for PassthroughAllocator {
+            fn alloc(&self, size: usize) -> Result<NonNull<u8>> {
+                LibcAllocator.alloc(size)
+            }
+
+            unsafe fn free(&self, ptr: NonNull<u8>) {
+                LibcAllocator.free(ptr)
+            }
+        }
+
+        let array = StringArray::new_with(PassthroughAllocator, &["a", "b"]).unwrap();
+        let ptr = array.into_raw();
+        let array = unsafe { StringArray::from_raw_with(ptr, true, PassthroughAllocator) };
+        let items: Vec<String> = array.into_iter().map(|x| x.unwrap()).collect();
+        assert_eq!(items, vec!["a", "b"]);
+    }
 }